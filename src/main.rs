@@ -1,10 +1,14 @@
 mod utils;
 
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use anyhow::{Context, Result};
 use clap::Parser;
 use glob::glob;
-use crate::utils::process_file;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use crate::utils::{chunk_file, format_size, process_file, Algorithm, ChunkerConfig, DedupStats, FileInfo};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -14,83 +18,452 @@ struct Cli {
     path: Vec<String>,
     #[arg(short, long, help = "Process directories recursively")]
     recursive: bool,
-    #[arg(long, help = "Show SHA256 checksum")]
+    #[arg(long, help = "Shorthand for `--algorithm sha256`")]
     sha: bool,
-    #[arg(long, help = "Show MD5 checksum")]
+    #[arg(long, help = "Shorthand for `--algorithm md5`")]
     md5: bool,
+    #[arg(
+        short = 'a',
+        long = "algorithm",
+        value_enum,
+        help = "Digest algorithm to compute (repeatable); defaults to sha256 and md5"
+    )]
+    algorithm: Vec<Algorithm>,
+    #[arg(
+        short = 'c',
+        long,
+        value_name = "MANIFEST",
+        help = "Verify files against a checksum manifest (coreutils sha256sum/md5sum -c format)"
+    )]
+    check: Option<String>,
+    #[arg(short, long, help = "With --check, only print failures and the summary")]
+    quiet: bool,
+    #[arg(
+        long,
+        help = "Report cross-file deduplication ratio using content-defined chunking"
+    )]
+    dedup: bool,
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = 2 * 1024,
+        help = "With --dedup, minimum chunk size"
+    )]
+    min_chunk: usize,
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = 8 * 1024,
+        help = "With --dedup, target average chunk size"
+    )]
+    avg_chunk: usize,
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = 64 * 1024,
+        help = "With --dedup, maximum chunk size (forced cut point)"
+    )]
+    max_chunk: usize,
+    #[arg(
+        short = 'j',
+        long,
+        default_value_t = 0,
+        help = "Number of parallel hashing jobs (0 = auto, one per core)"
+    )]
+    jobs: usize,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        help = "Output format for file reports"
+    )]
+    format: OutputFormat,
 }
 
-fn walk_and_process_dir(path: &Path, cli: &Cli) -> Result<()> {
-    for entry in std::fs::read_dir(path).context("Failed to read directory")? {
-        let entry = entry.context("Failed to read directory entry")?;
-        let current_path = entry.path();
+/// Output encoding for file reports; `sha256sum` emits the classic
+/// `HEX  path` layout so it can be fed straight back into `--check`.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+#[clap(rename_all = "lowercase")]
+enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+    Sha256sum,
+}
 
-        if current_path.is_file() {
-            println!("--- File: {} ---", current_path.display());
-            match process_file(&current_path) {
-                Ok(info) => {
-                    println!("  Size: {}", info.formatted_size);
-                    println!("  Type: {}", info.file_type);
-                    println!("  Encoding: {}", info.encoding);
-                    if cli.sha {
-                        println!("  SHA256: {}", info.sha256);
+/// A single file's outcome, shaped for serialization. Successful and failed
+/// files are both represented as structured records (rather than errors
+/// only going to stderr) so the `json`/`ndjson` formats stay machine-readable.
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+enum FileReport {
+    Ok {
+        path: String,
+        #[serde(flatten)]
+        info: FileInfo,
+    },
+    Error {
+        path: String,
+        error: String,
+    },
+}
+
+impl FileReport {
+    fn new(path: PathBuf, result: Result<FileInfo>) -> Self {
+        let path = path.display().to_string();
+        match result {
+            Ok(info) => FileReport::Ok { path, info },
+            Err(e) => FileReport::Error { path, error: e.to_string() },
+        }
+    }
+}
+
+/// Renders a batch of hashing results in the requested `--format`.
+fn emit_reports(results: Vec<(PathBuf, Result<FileInfo>)>, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Text => {
+            let mut error_count = 0usize;
+            for (path, result) in &results {
+                println!("--- File: {} ---", path.display());
+                match result {
+                    Ok(info) => {
+                        println!("  Size: {}", info.formatted_size);
+                        println!("  Type: {}", info.file_type);
+                        println!("  Encoding: {}", info.encoding);
+                        for (algorithm, digest) in &info.digests {
+                            println!("  {}: {}", algorithm.name(), digest);
+                        }
                     }
-                    if cli.md5 {
-                        println!("  MD5: {}", info.md5);
+                    Err(e) => {
+                        error_count += 1;
+                        eprintln!("  Error processing file {}: {}", path.display(), e);
                     }
                 }
-                Err(e) => {
-                    eprintln!("  Error processing file {}: {}", current_path.display(), e);
+                println!("----------------\n");
+            }
+            if error_count > 0 {
+                eprintln!(
+                    "check: {} of {} files could not be processed",
+                    error_count,
+                    results.len()
+                );
+            }
+        }
+        OutputFormat::Sha256sum => {
+            for (path, result) in &results {
+                match result {
+                    Ok(info) => match info.digest(Algorithm::Sha256) {
+                        Some(digest) => println!("{}  {}", digest, path.display()),
+                        None => eprintln!(
+                            "check: {}: no sha256 digest computed (pass --sha or -a sha256)",
+                            path.display()
+                        ),
+                    },
+                    Err(e) => eprintln!("  Error processing file {}: {}", path.display(), e),
                 }
             }
-            println!("----------------\n");
-        } else if current_path.is_dir() && cli.recursive {
-            println!("Processing directory: {}\n", current_path.display());
-            walk_and_process_dir(&current_path, cli)?;
+        }
+        OutputFormat::Json => {
+            let reports: Vec<FileReport> = results
+                .into_iter()
+                .map(|(path, result)| FileReport::new(path, result))
+                .collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&reports).context("Failed to serialize JSON report")?
+            );
+        }
+        OutputFormat::Ndjson => {
+            for (path, result) in results {
+                let report = FileReport::new(path, result);
+                println!(
+                    "{}",
+                    serde_json::to_string(&report).context("Failed to serialize NDJSON report")?
+                );
+            }
         }
     }
+
     Ok(())
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+/// One parsed line of a coreutils-style checksum manifest.
+struct ManifestEntry {
+    digest: String,
+    path: String,
+}
+
+/// Parses a single `HEX  FILENAME` / `HEX *FILENAME` manifest line.
+///
+/// Returns `None` for blank lines and lines that don't look like a checksum
+/// entry (comments, malformed rows), which callers report and skip.
+fn parse_manifest_line(line: &str) -> Option<ManifestEntry> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if line.is_empty() {
+        return None;
+    }
+
+    let sep_start = line.find(char::is_whitespace)?;
+    let digest = &line[..sep_start];
+    if digest.len() < 8 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let rest = &line[sep_start..];
+    let mut rest_indices = rest.char_indices();
+    let (_, first) = rest_indices.next()?;
+    if first != ' ' {
+        return None;
+    }
+    // The second separator char is ' ' for text mode or '*' for binary mode;
+    // `check` hashes every file the same way, so the mode itself is ignored.
+    let (_, second) = rest_indices.next()?;
+    if second != ' ' && second != '*' {
+        return None;
+    }
+    // Byte offset of the filename, just past the two separator chars.
+    let path_start = rest_indices.next().map_or(rest.len(), |(i, _)| i);
+
+    Some(ManifestEntry {
+        digest: digest.to_lowercase(),
+        path: rest[path_start..].to_string(),
+    })
+}
+
+/// Infers which algorithm produced a manifest digest, primarily from its
+/// hex length. A 64-char digest is ambiguous between SHA256 and BLAKE3, and
+/// any other unrecognized length falls back to whichever of `--sha`/`--md5`
+/// the caller passed (SHA256 if neither/both, matching `sha256sum`'s default).
+fn algorithm_for_digest_len(len: usize, cli: &Cli) -> Algorithm {
+    match len {
+        8 => Algorithm::Crc32,
+        32 => Algorithm::Md5,
+        40 => Algorithm::Sha1,
+        128 => Algorithm::Sha512,
+        _ if cli.md5 && !cli.sha => Algorithm::Md5,
+        _ => Algorithm::Sha256,
+    }
+}
+
+/// Verifies every file listed in `manifest_path` against a freshly computed
+/// digest, printing `FILENAME: OK`/`FILENAME: FAILED` as it goes.
+///
+/// Returns `Ok(true)` when every listed file was found and matched.
+fn run_check(manifest_path: &Path, cli: &Cli) -> Result<bool> {
+    let contents = std::fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
+
+    let mut mismatched: u64 = 0;
+    let mut unreadable: u64 = 0;
+
+    for (line_no, line) in contents.lines().enumerate() {
+        let entry = match parse_manifest_line(line) {
+            Some(entry) => entry,
+            None => {
+                if !line.trim().is_empty() {
+                    eprintln!(
+                        "check: {}: {}: improperly formatted checksum line",
+                        manifest_path.display(),
+                        line_no + 1
+                    );
+                }
+                continue;
+            }
+        };
+
+        let algorithm = algorithm_for_digest_len(entry.digest.len(), cli);
+
+        match process_file(Path::new(&entry.path), &[algorithm]) {
+            Ok(info) => {
+                let actual = info.digest(algorithm).unwrap_or_default();
+
+                if actual == entry.digest {
+                    if !cli.quiet {
+                        println!("{}: OK", entry.path);
+                    }
+                } else {
+                    mismatched += 1;
+                    println!("{}: FAILED", entry.path);
+                }
+            }
+            Err(e) => {
+                unreadable += 1;
+                println!("{}: FAILED open or read", entry.path);
+                eprintln!("check: {}: {}", entry.path, e);
+            }
+        }
+    }
 
+    if mismatched > 0 {
+        eprintln!(
+            "check: WARNING: {} computed checksum{} did NOT match",
+            mismatched,
+            if mismatched == 1 { "" } else { "s" }
+        );
+    }
+    if unreadable > 0 {
+        eprintln!(
+            "check: WARNING: {} listed file{} could not be read",
+            unreadable,
+            if unreadable == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(mismatched == 0 && unreadable == 0)
+}
+
+/// Recursively collects the files under `path` into `out`, descending into
+/// sub-directories only when `recursive` is set. Entries are sorted per
+/// directory so the resulting order (and therefore the final report order)
+/// doesn't depend on filesystem iteration order.
+fn collect_dir_targets(path: &Path, recursive: bool, out: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+        .context("Failed to read directory")?
+        .map(|entry| entry.context("Failed to read directory entry").map(|e| e.path()))
+        .collect::<Result<_>>()?;
+    entries.sort();
+
+    for current_path in entries {
+        if current_path.is_file() {
+            out.push(current_path);
+        } else if current_path.is_dir() && recursive {
+            collect_dir_targets(&current_path, recursive, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Resolves `--algorithm`/`--sha`/`--md5` into the set of digests to
+/// compute, defaulting to sha256 and md5 when none were requested.
+fn requested_algorithms(cli: &Cli) -> Vec<Algorithm> {
+    let mut algorithms = cli.algorithm.clone();
+    if cli.sha && !algorithms.contains(&Algorithm::Sha256) {
+        algorithms.push(Algorithm::Sha256);
+    }
+    if cli.md5 && !algorithms.contains(&Algorithm::Md5) {
+        algorithms.push(Algorithm::Md5);
+    }
+    if algorithms.is_empty() {
+        algorithms.push(Algorithm::Sha256);
+        algorithms.push(Algorithm::Md5);
+    }
+    algorithms
+}
+
+/// Expands every glob pattern in `cli.path` into a flat, ordered list of
+/// files to hash, recursing into directories per `--recursive`.
+fn collect_targets(cli: &Cli) -> Result<Vec<PathBuf>> {
+    let mut targets = Vec::new();
     for pattern in &cli.path {
-        for entry in glob(&pattern).context(format!("Failed to read glob pattern: {}", pattern))? {
+        for entry in glob(pattern).context(format!("Failed to read glob pattern: {}", pattern))? {
             match entry {
                 Ok(path) => {
                     if path.is_dir() {
-                        println!("Processing directory: {}\n", path.display());
-                        walk_and_process_dir(&path, &cli)?;
+                        collect_dir_targets(&path, cli.recursive, &mut targets)?;
                     } else if path.is_file() {
-                        println!("--- File: {} ---", path.display());
-                        match process_file(&path) {
-                            Ok(info) => {
-                                println!("  Size: {}", info.formatted_size);
-                                println!("  Type: {}", info.file_type);
-                                println!("  Encoding: {}", info.encoding);
-                                if cli.sha {
-                                    println!("  SHA256: {}", info.sha256);
-                                }
-                                if cli.md5 {
-                                    println!("  MD5: {}", info.md5);
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("  Error processing file: {}", e);
-                            }
-                        }
-                        println!("----------------\n");
+                        targets.push(path);
                     }
                 }
                 Err(e) => eprintln!("Error processing glob entry: {}", e),
             }
         }
     }
+    Ok(targets)
+}
+
+/// Runs `--dedup`: chunks every target with a shared `HashSet` of seen
+/// chunk digests so the reported ratio reflects duplication across the
+/// whole set of files, not just within each one.
+fn run_dedup(targets: &[PathBuf], cli: &Cli) -> Result<()> {
+    let config = ChunkerConfig {
+        min_size: cli.min_chunk,
+        avg_size: cli.avg_chunk,
+        max_size: cli.max_chunk,
+    };
+    let mut seen = HashSet::new();
+    let mut aggregate = DedupStats::default();
+
+    for path in targets {
+        match chunk_file(path, &config, &mut seen) {
+            Ok(stats) => {
+                println!(
+                    "{}: {} chunks, avg {}, {:.1}% unique",
+                    path.display(),
+                    stats.chunk_count,
+                    format_size(stats.average_chunk_size() as u64),
+                    stats.dedup_ratio() * 100.0
+                );
+                aggregate.chunk_count += stats.chunk_count;
+                aggregate.total_bytes += stats.total_bytes;
+                aggregate.unique_bytes += stats.unique_bytes;
+            }
+            Err(e) => eprintln!("Error chunking file {}: {}", path.display(), e),
+        }
+    }
+
+    println!(
+        "\nTotal: {} chunks, avg {}, {} unique of {} ({:.1}% unique)",
+        aggregate.chunk_count,
+        format_size(aggregate.average_chunk_size() as u64),
+        format_size(aggregate.unique_bytes),
+        format_size(aggregate.total_bytes),
+        aggregate.dedup_ratio() * 100.0
+    );
 
     Ok(())
 }
 
+/// Hashes `targets` across a `--jobs`-wide rayon pool, tracking progress on a
+/// bar, then prints results in input order so parallel completion order
+/// never leaks into the report.
+fn process_targets(targets: Vec<PathBuf>, cli: &Cli) -> Result<()> {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(cli.jobs)
+        .build()
+        .context("Failed to build thread pool")?;
+
+    let progress = ProgressBar::new(targets.len() as u64);
+    if let Ok(style) =
+        ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} files ({per_sec})")
+    {
+        progress.set_style(style);
+    }
+
+    let algorithms = requested_algorithms(cli);
+
+    let results: Vec<(PathBuf, Result<FileInfo>)> = pool.install(|| {
+        targets
+            .par_iter()
+            .map(|path| {
+                let result = process_file(path, &algorithms);
+                progress.inc(1);
+                (path.clone(), result)
+            })
+            .collect()
+    });
+    progress.finish_and_clear();
+
+    emit_reports(results, cli.format)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    if let Some(manifest) = cli.check.clone() {
+        let all_ok = run_check(Path::new(&manifest), &cli)?;
+        if !all_ok {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let targets = collect_targets(&cli)?;
+    if cli.dedup {
+        return run_dedup(&targets, &cli);
+    }
+    process_targets(targets, &cli)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,15 +484,15 @@ mod tests {
         let expected_md5 = "0d74ddaa1b80d2694f9137a9b87f5a57";
 
         // Process the file
-        let file_info = process_file(path).unwrap();
+        let file_info = process_file(path, &[Algorithm::Sha256, Algorithm::Md5]).unwrap();
 
         // Assertions
         assert_eq!(file_info.size, expected_size);
 
         // The old chardet version is not very accurate
         // assert_eq!(file_info.encoding, "UTF-8");
-        assert_eq!(file_info.sha256, expected_sha256);
-        assert_eq!(file_info.md5, expected_md5);
+        assert_eq!(file_info.digest(Algorithm::Sha256), Some(expected_sha256));
+        assert_eq!(file_info.digest(Algorithm::Md5), Some(expected_md5));
     }
 
     #[test]
@@ -141,5 +514,91 @@ mod tests {
         assert_eq!(format_size(1024 * 1024 * 1024 * 1024 * 1024 * 1024), "1.00 EB");
         assert_eq!(format_size(u64::MAX), "16.00 EB");
     }
+
+    #[test]
+    fn test_parse_manifest_line_text_mode() {
+        let entry = parse_manifest_line("deadbeefdeadbeefdeadbeefdeadbeef  file.txt").unwrap();
+        assert_eq!(entry.digest, "deadbeefdeadbeefdeadbeefdeadbeef");
+        assert_eq!(entry.path, "file.txt");
+    }
+
+    #[test]
+    fn test_parse_manifest_line_binary_mode() {
+        let entry = parse_manifest_line("deadbeefdeadbeefdeadbeefdeadbeef *file.bin").unwrap();
+        assert_eq!(entry.path, "file.bin");
+    }
+
+    #[test]
+    fn test_parse_manifest_line_uppercase_digest_is_lowercased() {
+        let entry = parse_manifest_line("DEADBEEFDEADBEEFDEADBEEFDEADBEEF  file.txt").unwrap();
+        assert_eq!(entry.digest, "deadbeefdeadbeefdeadbeefdeadbeef");
+    }
+
+    #[test]
+    fn test_parse_manifest_line_blank_is_none() {
+        assert!(parse_manifest_line("").is_none());
+        assert!(parse_manifest_line("   ").is_none());
+    }
+
+    #[test]
+    fn test_parse_manifest_line_malformed_is_none() {
+        assert!(parse_manifest_line("not a checksum line").is_none());
+        assert!(parse_manifest_line("# a comment").is_none());
+        assert!(parse_manifest_line("deadbeef").is_none());
+    }
+
+    #[test]
+    fn test_parse_manifest_line_multibyte_separator_does_not_panic() {
+        // A malformed line whose second separator char is multi-byte must be
+        // rejected as improperly formatted rather than sliced mid-codepoint.
+        assert!(parse_manifest_line("abcdef12 éfile.txt").is_none());
+    }
+
+    #[test]
+    fn test_chunk_file_is_deterministic() {
+        let content: Vec<u8> = (0..20_000u32).map(|i| (i % 97) as u8).collect();
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        temp_file.write_all(&content).unwrap();
+
+        let config = ChunkerConfig::default();
+
+        let mut seen_a = HashSet::new();
+        let stats_a = chunk_file(temp_file.path(), &config, &mut seen_a).unwrap();
+
+        let mut seen_b = HashSet::new();
+        let stats_b = chunk_file(temp_file.path(), &config, &mut seen_b).unwrap();
+
+        assert_eq!(stats_a.chunk_count, stats_b.chunk_count);
+        assert_eq!(stats_a.total_bytes, stats_b.total_bytes);
+        assert_eq!(seen_a, seen_b, "the same bytes must always cut into the same chunk digests");
+    }
+
+    #[test]
+    fn test_chunk_file_cross_file_dedup_via_shared_seen_set() {
+        let content: Vec<u8> = (0..40_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut first_file = tempfile::NamedTempFile::new().unwrap();
+        first_file.write_all(&content).unwrap();
+        let mut second_file = tempfile::NamedTempFile::new().unwrap();
+        second_file.write_all(&content).unwrap();
+
+        let config = ChunkerConfig::default();
+        let mut seen = HashSet::new();
+
+        let first_stats = chunk_file(first_file.path(), &config, &mut seen).unwrap();
+        assert!(first_stats.chunk_count > 0);
+        assert_eq!(
+            first_stats.unique_bytes, first_stats.total_bytes,
+            "the first file's chunks have never been seen before"
+        );
+
+        let second_stats = chunk_file(second_file.path(), &config, &mut seen).unwrap();
+        assert_eq!(second_stats.chunk_count, first_stats.chunk_count);
+        assert_eq!(second_stats.total_bytes, first_stats.total_bytes);
+        assert_eq!(
+            second_stats.unique_bytes, 0,
+            "an identical second file should contribute no new unique bytes"
+        );
+    }
 }
 